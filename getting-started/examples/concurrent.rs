@@ -0,0 +1,48 @@
+//! Demonstrates that wgpu resource creation and submission are thread-safe:
+//! many independent jobs are dispatched concurrently from separate threads
+//! against a single shared `Device`/`Queue`, each building its own buffers,
+//! bind group, and command encoder.
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use getting_started::Gpu;
+
+const JOBS: usize = 8;
+
+fn main() {
+    pollster::block_on(async_main());
+}
+
+async fn async_main() {
+    let gpu = Arc::new(Gpu::new().await);
+    let (sender, receiver) = mpsc::channel();
+
+    let handles: Vec<_> = (0..JOBS)
+        .map(|job| {
+            let gpu = Arc::clone(&gpu);
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let input: Vec<f32> = (0..1028).map(|x| (x + job) as f32).collect();
+                let result = gpu.map_unary(&input, "cos(x.data[gidx])");
+                sender.send((job, result)).expect("receiver dropped");
+            })
+        })
+        .collect();
+    drop(sender);
+
+    for handle in handles {
+        handle.join().expect("worker thread panicked");
+    }
+
+    let mut results: Vec<(usize, Vec<f32>)> = receiver.iter().collect();
+    results.sort_by_key(|(job, _)| *job);
+
+    for (job, result) in &results {
+        println!("job {}: {:?}", job, &result[0..5]);
+    }
+    println!(
+        "Ran {} concurrent jobs against one shared device",
+        results.len()
+    );
+}
@@ -0,0 +1,837 @@
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+/// Workgroup size used by the generated kernels below. Tune this
+/// (32/64/128/256) and benchmark for your workload and GPU.
+const WG: u32 = 64;
+
+/// Reads `WGPU_BACKEND` (vulkan|metal|dx12|dx11|gl|webgpu), falling back to
+/// all backends if unset or unrecognized.
+fn backends_from_env() -> wgpu::Backends {
+    match std::env::var("WGPU_BACKEND") {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "vulkan" => wgpu::Backends::VULKAN,
+            "metal" => wgpu::Backends::METAL,
+            "dx12" => wgpu::Backends::DX12,
+            "dx11" => wgpu::Backends::DX11,
+            "gl" => wgpu::Backends::GL,
+            "webgpu" => wgpu::Backends::BROWSER_WEBGPU,
+            _ => wgpu::Backends::all(),
+        },
+        Err(_) => wgpu::Backends::all(),
+    }
+}
+
+/// Reads `WGPU_POWER_PREF` (low|high), falling back to wgpu's default
+/// preference if unset or unrecognized.
+fn power_preference_from_env() -> wgpu::PowerPreference {
+    match std::env::var("WGPU_POWER_PREF") {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "low" => wgpu::PowerPreference::LowPower,
+            "high" => wgpu::PowerPreference::HighPerformance,
+            _ => wgpu::PowerPreference::default(),
+        },
+        Err(_) => wgpu::PowerPreference::default(),
+    }
+}
+
+/// Renders a `struct NAME { data: [[stride(4)]] array<ELEM>; };` WGSL
+/// struct definition. The generated kernels below bind one of these per
+/// `f32` data buffer, so the shape is shared instead of being restated per
+/// method.
+fn array_struct(name: &str, elem_ty: &str) -> String {
+    format!(
+        "struct {name} {{\n    data: [[stride(4)]] array<{elem_ty}>;\n}};",
+        name = name,
+        elem_ty = elem_ty
+    )
+}
+
+/// Renders a `var<storage, ACCESS> NAME: STRUCT_NAME;` binding declaration
+/// at `group(0), binding(BINDING)`.
+fn storage_binding(binding: u32, access: &str, name: &str, struct_name: &str) -> String {
+    format!(
+        "[[group(0), binding({binding})]]\nvar<storage, {access}> {name}: {struct_name};",
+        binding = binding,
+        access = access,
+        name = name,
+        struct_name = struct_name
+    )
+}
+
+/// A thin wrapper around the wgpu instance/adapter/device/queue boilerplate,
+/// exposing elementwise compute kernels as plain Rust functions so callers
+/// don't have to hand-roll a shader/pipeline/bind group for every operation.
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl Gpu {
+    /// Requests an adapter and device, honoring the `WGPU_BACKEND`,
+    /// `WGPU_POWER_PREF` (`low`|`high`) and `WGPU_ADAPTER_NAME` environment
+    /// variables so users on multi-GPU machines or pinned CI runners can
+    /// pick which GPU runs the job. Prints the name/backend of the adapter
+    /// that was selected.
+    pub async fn new() -> Self {
+        let backends = backends_from_env();
+        let instance = wgpu::Instance::new(backends);
+
+        let adapter = if let Ok(name) = std::env::var("WGPU_ADAPTER_NAME") {
+            instance
+                .enumerate_adapters(backends)
+                .find(|adapter| {
+                    adapter
+                        .get_info()
+                        .name
+                        .to_lowercase()
+                        .contains(&name.to_lowercase())
+                })
+                .unwrap_or_else(|| panic!("No adapter matching WGPU_ADAPTER_NAME={:?} found", name))
+        } else {
+            instance
+                .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                    power_preference: power_preference_from_env(),
+                    ..Default::default()
+                })
+                .await
+                .expect("No GPU Found for referenced preference")
+        };
+
+        let info = adapter.get_info();
+        println!("Using adapter {:?} ({:?})", info.name, info.backend);
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .expect("Could not create adapter for GPU device");
+
+        Self { device, queue }
+    }
+
+    /// Runs a unary elementwise kernel over `input`, returning one output
+    /// value per input element. `wgsl_body` is a WGSL expression evaluated
+    /// per-lane with `x.data[gidx]` as the current input element, e.g.
+    /// `"cos(x.data[gidx])"`.
+    pub fn map_unary(&self, input: &[f32], wgsl_body: &str) -> Vec<f32> {
+        let shader = format!(
+            "
+{array_struct}
+
+{x_binding}
+
+{y_binding}
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let gidx = global_id.x;
+    if (gidx >= arrayLength(&x.data)) {{
+        return;
+    }}
+    y.data[gidx] = {body};
+}}
+    ",
+            array_struct = array_struct("Array", "f32"),
+            x_binding = storage_binding(0, "read", "x", "Array"),
+            y_binding = storage_binding(1, "write", "y", "Array"),
+            wg = WG,
+            body = wgsl_body
+        );
+
+        let buffer_size = (input.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+
+        let x_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("x"),
+                contents: bytemuck::cast_slice(input),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+
+        let y_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let pipeline = self.make_pipeline(&shader);
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: x_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: y_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.dispatch(
+            &pipeline,
+            &bind_group,
+            input.len() as u32,
+            &y_buffer,
+            buffer_size,
+        )
+    }
+
+    /// Runs a binary elementwise kernel over `lhs`/`rhs` (which must have
+    /// the same length), returning one output value per element. `wgsl_body`
+    /// is a WGSL expression with `x.data[gidx]` and `y.data[gidx]` as the
+    /// current lhs/rhs elements, e.g. `"x.data[gidx] + y.data[gidx]"`.
+    pub fn map_binary(&self, lhs: &[f32], rhs: &[f32], wgsl_body: &str) -> Vec<f32> {
+        assert_eq!(
+            lhs.len(),
+            rhs.len(),
+            "map_binary inputs must have the same length"
+        );
+
+        let shader = format!(
+            "
+{array_struct}
+
+{x_binding}
+
+{y_binding}
+
+{out_binding}
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let gidx = global_id.x;
+    if (gidx >= arrayLength(&x.data)) {{
+        return;
+    }}
+    out.data[gidx] = {body};
+}}
+    ",
+            array_struct = array_struct("Array", "f32"),
+            x_binding = storage_binding(0, "read", "x", "Array"),
+            y_binding = storage_binding(1, "read", "y", "Array"),
+            out_binding = storage_binding(2, "write", "out", "Array"),
+            wg = WG,
+            body = wgsl_body
+        );
+
+        let buffer_size = (lhs.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+
+        let x_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("x"),
+                contents: bytemuck::cast_slice(lhs),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+
+        let y_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("y"),
+                contents: bytemuck::cast_slice(rhs),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+
+        let out_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let pipeline = self.make_pipeline(&shader);
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: x_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: y_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: out_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.dispatch(
+            &pipeline,
+            &bind_group,
+            lhs.len() as u32,
+            &out_buffer,
+            buffer_size,
+        )
+    }
+
+    /// Runs an ordered list of unary WGSL stages over `input` in a single
+    /// command encoder, each stage reading the previous stage's output.
+    /// Stages ping-pong between two storage buffers so intermediate results
+    /// never round-trip to the CPU; only the final buffer is read back.
+    /// Each `stages` entry is a WGSL expression like `map_unary`'s
+    /// `wgsl_body`, e.g. `&["x.data[gidx] * 2.0", "x.data[gidx] + 1.0"]`.
+    pub fn map_pipeline(&self, input: &[f32], stages: &[&str]) -> Vec<f32> {
+        assert!(
+            !stages.is_empty(),
+            "map_pipeline requires at least one stage"
+        );
+
+        let buffer_size = (input.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+
+        let buffers = [
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("ping"),
+                    contents: bytemuck::cast_slice(input),
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                }),
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("pong"),
+                size: buffer_size,
+                mapped_at_creation: false,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            }),
+        ];
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        let mut pipelines = Vec::with_capacity(stages.len());
+        let mut bind_groups = Vec::with_capacity(stages.len());
+        for (i, body) in stages.iter().enumerate() {
+            let (read_buffer, write_buffer) = (&buffers[i % 2], &buffers[(i + 1) % 2]);
+
+            let shader = format!(
+                "
+{array_struct}
+
+{x_binding}
+
+{y_binding}
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let gidx = global_id.x;
+    if (gidx >= arrayLength(&x.data)) {{
+        return;
+    }}
+    y.data[gidx] = {body};
+}}
+    ",
+                array_struct = array_struct("Array", "f32"),
+                x_binding = storage_binding(0, "read", "x", "Array"),
+                y_binding = storage_binding(1, "write", "y", "Array"),
+                wg = WG,
+                body = body
+            );
+
+            let pipeline = self.make_pipeline(&shader);
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: read_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: write_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bind_group, &[]);
+                cpass.dispatch((input.len() as u32 + WG - 1) / WG, 1, 1);
+            }
+
+            // Keep the pipeline/bind group alive until the encoder is submitted.
+            pipelines.push(pipeline);
+            bind_groups.push(bind_group);
+        }
+
+        let final_buffer = &buffers[stages.len() % 2];
+
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        });
+        encoder.copy_buffer_to_buffer(final_buffer, 0, &staging_buffer, 0, buffer_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+
+        self.device.poll(wgpu::Maintain::Wait);
+
+        pollster::block_on(buffer_future).expect("failed to run compute on gpu!");
+        let data = buffer_slice.get_mapped_range();
+        let result: Vec<f32> = bytemuck::cast_slice(&data).to_vec();
+        drop(data);
+        staging_buffer.unmap();
+
+        result
+    }
+
+    /// Repeatedly dispatches the same kernel over a single `read_write`
+    /// storage buffer within one submission, so each iteration sees the
+    /// previous iteration's writes via the implicit barrier between compute
+    /// dispatches. `wgsl_body` is a WGSL statement (not just an expression)
+    /// that updates `x.data[gidx]` in place, e.g. `"x.data[gidx] = x.data[gidx] + 1.0;"`.
+    /// `data` is updated in place with the result after `iterations` passes.
+    pub fn iterate(&self, data: &mut [f32], wgsl_body: &str, iterations: u32) {
+        let shader = format!(
+            "
+{array_struct}
+
+{x_binding}
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let gidx = global_id.x;
+    if (gidx >= arrayLength(&x.data)) {{
+        return;
+    }}
+    {body}
+}}
+    ",
+            array_struct = array_struct("Array", "f32"),
+            x_binding = storage_binding(0, "read_write", "x", "Array"),
+            wg = WG,
+            body = wgsl_body
+        );
+
+        let buffer_size = (data.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+
+        let x_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("x"),
+                contents: bytemuck::cast_slice(data),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        let pipeline = self.make_pipeline(&shader);
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: x_buffer.as_entire_binding(),
+            }],
+        });
+
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut cpass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            for _ in 0..iterations {
+                cpass.dispatch((data.len() as u32 + WG - 1) / WG, 1, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&x_buffer, 0, &staging_buffer, 0, buffer_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+
+        self.device.poll(wgpu::Maintain::Wait);
+
+        pollster::block_on(buffer_future).expect("failed to run compute on gpu!");
+        let mapped = buffer_slice.get_mapped_range();
+        data.copy_from_slice(bytemuck::cast_slice(&mapped));
+        drop(mapped);
+        staging_buffer.unmap();
+    }
+
+    /// Runs a unary kernel over only the input elements that satisfy
+    /// `count_wgsl_cond` (a WGSL boolean expression over `x.data[gidx]`),
+    /// with the GPU computing its own dispatch size instead of the caller
+    /// doing so up front. A "count" pass evaluates `count_wgsl_cond` for
+    /// every input element, atomically counts how many pass, and scatters
+    /// their original indices into a compact buffer (stream compaction). A
+    /// tiny "prepare" pass turns that count into a clamped `[u32; 3]`
+    /// workgroup count in an indirect buffer. The main kernel (`wgsl_body`,
+    /// same shape as `map_unary`'s) is then dispatched via
+    /// `dispatch_indirect`, with `gidx` rebound to each matching element's
+    /// original index so it only ever runs over actual matches rather than
+    /// a raw index prefix. Output elements that didn't match
+    /// `count_wgsl_cond` are left at their default (zeroed) value.
+    pub fn map_indirect(&self, input: &[f32], count_wgsl_cond: &str, wgsl_body: &str) -> Vec<f32> {
+        let buffer_size = (input.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+        let indices_buffer_size = (input.len() * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+
+        let x_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("x"),
+                contents: bytemuck::cast_slice(input),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+
+        let y_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        // Counts, in a single atomic, how many input elements satisfy
+        // `count_wgsl_cond`.
+        let count_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("count"),
+                contents: bytemuck::cast_slice(&[0u32]),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        // Holds the original index of every element that matched
+        // `count_wgsl_cond`, written by the count pass in the order the
+        // atomic hands out slots, then read sequentially by the main pass.
+        let compact_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("compact"),
+            size: indices_buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let count_shader = format!(
+            "
+{array_struct}
+
+struct Count {{
+    value: atomic<u32>;
+}};
+
+struct Indices {{
+    data: [[stride(4)]] array<u32>;
+}};
+
+{x_binding}
+
+[[group(0), binding(1)]]
+var<storage, read_write> count: Count;
+
+[[group(0), binding(2)]]
+var<storage, write> compact: Indices;
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let gidx = global_id.x;
+    if (gidx >= arrayLength(&x.data)) {{
+        return;
+    }}
+    if ({cond}) {{
+        let slot = atomicAdd(&count.value, 1u);
+        compact.data[slot] = gidx;
+    }}
+}}
+    ",
+            array_struct = array_struct("Array", "f32"),
+            x_binding = storage_binding(0, "read", "x", "Array"),
+            wg = WG,
+            cond = count_wgsl_cond
+        );
+
+        let count_pipeline = self.make_pipeline(&count_shader);
+        let count_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &count_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: x_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: count_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: compact_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // `[u32; 3]` dispatch-indirect args, initialized to a harmless 1x1x1
+        // grid until the "prepare" pass below overwrites them.
+        let indirect_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("indirect"),
+                contents: bytemuck::cast_slice(&[1u32, 1u32, 1u32]),
+                usage: wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        // Turns the active-element count into a workgroup count, clamped to
+        // the device's limit so an unexpectedly large count can't be read
+        // back by `dispatch_indirect` and cause a device loss.
+        let max_workgroups = self.device.limits().max_compute_workgroups_per_dimension;
+        let prepare_shader = format!(
+            "
+struct Count {{
+    value: atomic<u32>;
+}};
+
+struct Indirect {{
+    data: array<u32, 3>;
+}};
+
+[[group(0), binding(0)]]
+var<storage, read> count: Count;
+
+[[group(0), binding(1)]]
+var<storage, write> indirect: Indirect;
+
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {{
+    let active = atomicLoad(&count.value);
+    let workgroups = (active + {wg}u - 1u) / {wg}u;
+    indirect.data[0] = max(min(workgroups, {max_wg}u), 1u);
+    indirect.data[1] = 1u;
+    indirect.data[2] = 1u;
+}}
+    ",
+            wg = WG,
+            max_wg = max_workgroups
+        );
+
+        let prepare_pipeline = self.make_pipeline(&prepare_shader);
+        let prepare_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &prepare_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: count_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: indirect_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // `slot` walks the compacted indices (one per dispatched lane, up to
+        // `count.value` of them); `gidx` is rebound to the original element
+        // index that slot refers to before `wgsl_body` runs, so callers can
+        // keep writing `x.data[gidx]`/`y.data[gidx]` exactly like `map_unary`.
+        let main_shader = format!(
+            "
+{array_struct}
+
+struct CountValue {{
+    value: u32;
+}};
+
+struct Indices {{
+    data: [[stride(4)]] array<u32>;
+}};
+
+{x_binding}
+
+{y_binding}
+
+[[group(0), binding(2)]]
+var<storage, read> compact: Indices;
+
+[[group(0), binding(3)]]
+var<storage, read> count: CountValue;
+
+[[stage(compute), workgroup_size({wg}, 1, 1)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {{
+    let slot = global_id.x;
+    if (slot >= count.value) {{
+        return;
+    }}
+    let gidx = compact.data[slot];
+    y.data[gidx] = {body};
+}}
+    ",
+            array_struct = array_struct("Array", "f32"),
+            x_binding = storage_binding(0, "read", "x", "Array"),
+            y_binding = storage_binding(1, "write", "y", "Array"),
+            wg = WG,
+            body = wgsl_body
+        );
+
+        let main_pipeline = self.make_pipeline(&main_shader);
+        let main_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &main_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: x_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: y_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: compact_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: count_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut cpass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+
+            cpass.set_pipeline(&count_pipeline);
+            cpass.set_bind_group(0, &count_bind_group, &[]);
+            cpass.dispatch((input.len() as u32 + WG - 1) / WG, 1, 1);
+
+            cpass.set_pipeline(&prepare_pipeline);
+            cpass.set_bind_group(0, &prepare_bind_group, &[]);
+            cpass.dispatch(1, 1, 1);
+
+            cpass.set_pipeline(&main_pipeline);
+            cpass.set_bind_group(0, &main_bind_group, &[]);
+            cpass.dispatch_indirect(&indirect_buffer, 0);
+        }
+        encoder.copy_buffer_to_buffer(&y_buffer, 0, &staging_buffer, 0, buffer_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+
+        self.device.poll(wgpu::Maintain::Wait);
+
+        pollster::block_on(buffer_future).expect("failed to run compute on gpu!");
+        let data = buffer_slice.get_mapped_range();
+        let result: Vec<f32> = bytemuck::cast_slice(&data).to_vec();
+        drop(data);
+        staging_buffer.unmap();
+
+        result
+    }
+
+    fn make_pipeline(&self, shader: &str) -> wgpu::ComputePipeline {
+        self.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                module: &self
+                    .device
+                    .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                        label: None,
+                        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader)),
+                    }),
+                entry_point: "main",
+            })
+    }
+
+    /// Dispatches `pipeline`/`bind_group` over `len` elements and reads
+    /// `output_buffer` back to the host via a staging buffer.
+    fn dispatch(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        bind_group: &wgpu::BindGroup,
+        len: u32,
+        output_buffer: &wgpu::Buffer,
+        buffer_size: wgpu::BufferAddress,
+    ) -> Vec<f32> {
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: buffer_size,
+            mapped_at_creation: false,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut cpass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch((len + WG - 1) / WG, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(output_buffer, 0, &staging_buffer, 0, buffer_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+
+        self.device.poll(wgpu::Maintain::Wait);
+
+        pollster::block_on(buffer_future).expect("failed to run compute on gpu!");
+        let data = buffer_slice.get_mapped_range();
+        let result: Vec<f32> = bytemuck::cast_slice(&data).to_vec();
+        drop(data);
+        staging_buffer.unmap();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gpu;
+
+    #[test]
+    fn iterate_accumulates_across_dispatches() {
+        pollster::block_on(async {
+            let gpu = Gpu::new().await;
+            let iterations = 100;
+            let mut data = vec![0.0f32; 256];
+
+            gpu.iterate(&mut data, "x.data[gidx] = x.data[gidx] + 1.0;", iterations);
+
+            for value in data {
+                assert_eq!(value, iterations as f32);
+            }
+        });
+    }
+}